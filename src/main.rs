@@ -1,15 +1,25 @@
 #[allow(unused_imports)]
 use std::net::TcpListener;
 use std::{
+    collections::HashMap,
     env, fs,
-    io::{Read, Write},
+    io::{Read, Seek, SeekFrom, Write},
     net::TcpStream,
     path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
+use flate2::{write::GzEncoder, Compression};
 use tokio;
 
 const CLRF: &str = "\r\n";
 
+/// How long a blocking `read` on a connection's stream may idle before it
+/// gives up. Without this, a client that opens a keep-alive connection and
+/// never sends anything (or never sends `Connection: close`) pins the tokio
+/// worker thread handling it forever, since these reads are synchronous and
+/// run directly inside the spawned task rather than via `spawn_blocking`.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
 #[derive(PartialEq, Debug)]
 enum HttpMethod {
     GET,
@@ -28,6 +38,12 @@ enum HttpVersion {
 enum HttpResponseHeaders {
     ContentType,
     ContentLength,
+    ContentEncoding,
+    Connection,
+    ETag,
+    LastModified,
+    AcceptRanges,
+    ContentRange,
 }
 
 impl HttpResponseHeaders {
@@ -35,6 +51,12 @@ impl HttpResponseHeaders {
         match self {
             HttpResponseHeaders::ContentType => "Content-Type: ",
             HttpResponseHeaders::ContentLength => "Content-Length: ",
+            HttpResponseHeaders::ContentEncoding => "Content-Encoding: ",
+            HttpResponseHeaders::Connection => "Connection: ",
+            HttpResponseHeaders::ETag => "ETag: ",
+            HttpResponseHeaders::LastModified => "Last-Modified: ",
+            HttpResponseHeaders::AcceptRanges => "Accept-Ranges: ",
+            HttpResponseHeaders::ContentRange => "Content-Range: ",
         }
     }
 }
@@ -69,8 +91,14 @@ enum HttpRequestHeaders {
     UserAgent,
     Host,
     Accept,
+    AcceptEncoding,
     ContentType,
     ContentLength,
+    Connection,
+    IfNoneMatch,
+    IfModifiedSince,
+    Range,
+    Expect,
 }
 
 impl HttpRequestHeaders {
@@ -79,8 +107,14 @@ impl HttpRequestHeaders {
             HttpRequestHeaders::UserAgent => "User-Agent",
             HttpRequestHeaders::Host => "Host",
             HttpRequestHeaders::Accept => "Accept",
+            HttpRequestHeaders::AcceptEncoding => "Accept-Encoding",
             HttpRequestHeaders::ContentType => "Content-Type",
             HttpRequestHeaders::ContentLength => "Content-Length",
+            HttpRequestHeaders::Connection => "Connection",
+            HttpRequestHeaders::IfNoneMatch => "If-None-Match",
+            HttpRequestHeaders::IfModifiedSince => "If-Modified-Since",
+            HttpRequestHeaders::Range => "Range",
+            HttpRequestHeaders::Expect => "Expect",
         }
     }
 
@@ -90,8 +124,14 @@ impl HttpRequestHeaders {
             "user-agent" => Some(HttpRequestHeaders::UserAgent),
             "host" => Some(HttpRequestHeaders::Host),
             "accept" => Some(HttpRequestHeaders::Accept),
+            "accept-encoding" => Some(HttpRequestHeaders::AcceptEncoding),
             "content-type" => Some(HttpRequestHeaders::ContentType),
             "content-length" => Some(HttpRequestHeaders::ContentLength),
+            "connection" => Some(HttpRequestHeaders::Connection),
+            "if-none-match" => Some(HttpRequestHeaders::IfNoneMatch),
+            "if-modified-since" => Some(HttpRequestHeaders::IfModifiedSince),
+            "range" => Some(HttpRequestHeaders::Range),
+            "expect" => Some(HttpRequestHeaders::Expect),
             _ => None,
         }
     }
@@ -159,6 +199,37 @@ impl HttpRequestHeaderParser {
         self.get_content_type()
             .map_or(false, |ct| ct == content_type)
     }
+
+    /// Checks whether `token` (e.g. "gzip") appears in the comma-separated
+    /// `Accept-Encoding` header, ignoring q-values and case.
+    pub fn accepts_encoding(&self, token: &str) -> bool {
+        self.get_header_value(HttpRequestHeaders::AcceptEncoding)
+            .map_or(false, |value| {
+                value.split(',').any(|encoding| {
+                    encoding
+                        .trim()
+                        .split(';')
+                        .next()
+                        .unwrap_or("")
+                        .trim()
+                        .eq_ignore_ascii_case(token)
+                })
+            })
+    }
+
+    /// HTTP/1.1 connections default to keep-alive; this is true only when the
+    /// client explicitly asked to close the connection after this request.
+    pub fn wants_close(&self) -> bool {
+        self.get_header_value(HttpRequestHeaders::Connection)
+            .map_or(false, |value| value.eq_ignore_ascii_case("close"))
+    }
+
+    /// True when the client pre-flighted its body with `Expect: 100-continue`
+    /// and is waiting for a `100 Continue` before sending it.
+    pub fn wants_continue(&self) -> bool {
+        self.get_header_value(HttpRequestHeaders::Expect)
+            .map_or(false, |value| value.eq_ignore_ascii_case("100-continue"))
+    }
 }
 
 struct HttpRequestBody {
@@ -196,51 +267,251 @@ async fn main() {
 }
 
 async fn handle_connection(mut stream: TcpStream) {
-    let mut raw_request: [u8; 1024] = [0; 1024]; // the request is assumed to be less than 1024 bytes
-    stream.read(&mut raw_request).unwrap();
+    let router = build_router();
+    let mut reader = RequestReader::new(&mut stream);
+
+    loop {
+        let (request_line, headers) = match reader.read_headers() {
+            Some(parsed) => parsed,
+            None => return, // peer half-closed the connection, or the read failed/timed out
+        };
 
-    // Split the raw request into headers and body sections
-    let raw_request_str = String::from_utf8_lossy(&raw_request);
-    let parts: Vec<&str> = raw_request_str.split(&CLRF.repeat(2)).collect(); // two CLRF characters seems to seperate the request line+headers and body
+        let (method, path, version) = match parse_request_line(&request_line) {
+            Some(parsed) => parsed,
+            None => {
+                respond_bad_request(
+                    &mut *reader.stream,
+                    None,
+                    Some("malformed request line.".to_string()),
+                    false,
+                );
+                return;
+            }
+        };
 
-    // Parse headers section
-    let header_section = parts[0];
-    let mut lines = header_section.lines();
-    let request_line = lines.next().unwrap_or("");
-    let headers = lines.collect::<Vec<&str>>();
+        if version != HttpVersion::V1_1 {
+            respond_bad_request(
+                &mut *reader.stream,
+                None,
+                Some("this server only supports HTTP version 1.1.".to_string()),
+                false,
+            );
+            return;
+        }
+
+        let mut header_parser = HttpRequestHeaderParser::new();
+        header_parser.parse(&headers.iter().map(|h| h.as_str()).collect::<Vec<&str>>());
+        let keep_alive = !header_parser.wants_close();
+
+        // Parse body if one was declared
+        let content_length = header_parser
+            .get_header_value(HttpRequestHeaders::ContentLength)
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(0);
+
+        if content_length > 0 && header_parser.wants_continue() {
+            respond_continue(&mut *reader.stream);
+        }
+
+        let body = if content_length > 0 {
+            Some(HttpRequestBody::new(reader.read_body(content_length)))
+        } else {
+            None
+        };
+
+        let keep_alive = match router.match_route(&method, path) {
+            Some((handler, params)) => {
+                handler(&mut *reader.stream, &params, &header_parser, body, keep_alive)
+            }
+            None => respond_not_found(&mut *reader.stream, None, None, keep_alive),
+        };
+
+        if !keep_alive {
+            return;
+        }
+    }
+}
 
-    let (method, path, version) = parse_request_line(request_line).unwrap();
+/// Wraps a `TcpStream` and an internal byte buffer so requests larger than a
+/// single `read` call, or with binary (non-UTF-8) bodies, can be parsed
+/// without corrupting the body. Bytes read past one request's boundary stay
+/// buffered for the next call, which is what makes keep-alive pipelining work.
+struct RequestReader<'a> {
+    stream: &'a mut TcpStream,
+    buffer: Vec<u8>,
+}
 
-    if version != HttpVersion::V1_1 {
-        respond_bad_request(
+impl<'a> RequestReader<'a> {
+    pub fn new(stream: &'a mut TcpStream) -> Self {
+        stream.set_read_timeout(Some(READ_TIMEOUT)).unwrap();
+        RequestReader {
             stream,
-            None,
-            Some("this server only supports HTTP version 1.1.".to_string()),
-        );
-        return;
+            buffer: Vec::new(),
+        }
     }
 
-    let mut header_parser = HttpRequestHeaderParser::new();
-    header_parser.parse(&headers);
+    /// Reads (and buffers) bytes until a full request-line+headers block is
+    /// available, then returns the request line and header lines. Returns
+    /// `None` once the peer has half-closed the connection, or when a read
+    /// fails or times out (see `READ_TIMEOUT`).
+    pub fn read_headers(&mut self) -> Option<(String, Vec<String>)> {
+        let boundary = loop {
+            if let Some(pos) = find_subslice(&self.buffer, CLRF.repeat(2).as_bytes()) {
+                break pos;
+            }
 
-    // Parse body if it exists
-    let body = if parts.len() > 1 {
-        let body_content = parts[1].as_bytes().to_vec();
-        Some(HttpRequestBody::new(body_content))
-    } else {
-        None
-    };
+            let mut chunk = [0u8; 1024];
+            let n = self.stream.read(&mut chunk).ok()?;
+            if n == 0 {
+                return None;
+            }
+            self.buffer.extend_from_slice(&chunk[..n]);
+        };
+
+        let header_end = boundary + CLRF.repeat(2).len();
+        let header_bytes: Vec<u8> = self.buffer.drain(..header_end).collect();
+        let header_section = String::from_utf8_lossy(&header_bytes[..boundary]);
+
+        let mut lines = header_section.lines();
+        let request_line = lines.next().unwrap_or("").to_string();
+        let headers = lines.map(|line| line.to_string()).collect();
+        Some((request_line, headers))
+    }
+
+    /// Reads exactly `content_length` body bytes, using bytes already
+    /// buffered by `read_headers` first and looping on `read` until the rest
+    /// of the body has arrived. If the peer closes the connection or a read
+    /// fails first, returns only the bytes that did arrive. The body is kept
+    /// as raw bytes throughout, so binary uploads are never run through a
+    /// lossy UTF-8 conversion.
+    pub fn read_body(&mut self, content_length: usize) -> Vec<u8> {
+        while self.buffer.len() < content_length {
+            let mut chunk = [0u8; 1024];
+            match self.stream.read(&mut chunk) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => self.buffer.extend_from_slice(&chunk[..n]),
+            }
+        }
+        let take = content_length.min(self.buffer.len());
+        self.buffer.drain(..take).collect()
+    }
+}
+
+fn find_subslice(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// A handler registered with the `Router`. All endpoints share this shape so
+/// the router can store them as plain function pointers: the matched path
+/// params, the parsed request headers, an already-read body (if any), and
+/// whether the connection should stay alive after this response.
+type Handler =
+    fn(&mut TcpStream, &HashMap<String, String>, &HttpRequestHeaderParser, Option<HttpRequestBody>, bool) -> bool;
 
-    match method {
-        HttpMethod::GET if path == "/" => respond_ok(stream, None, None),
-        HttpMethod::GET if path.starts_with("/echo/") => endpoint_get_echo(stream, path),
-        HttpMethod::GET if path == "/user-agent" => endpoint_get_user_agent(stream, &header_parser),
-        HttpMethod::GET if path.starts_with("/files/") => endpoint_get_files(stream, path),
-        HttpMethod::POST if path.starts_with("/files/") => {
-            endpoint_post_files(stream, path, &header_parser, body)
+/// One segment of a registered route pattern, e.g. `/echo/:msg` parses into
+/// `[Static("echo"), Param("msg")]`.
+enum RouteSegment {
+    Static(String),
+    Param(String),
+    Wildcard(String),
+}
+
+struct Route {
+    method: HttpMethod,
+    segments: Vec<RouteSegment>,
+    handler: Handler,
+}
+
+/// A small route-recognizer-style router: register `METHOD /pattern/:with/*tails`
+/// once, then match an incoming method+path against every registered route,
+/// extracting `:param` and `*wildcard` segments into a params map. On ties,
+/// static segments beat `:param`, which beats `*wildcard`.
+struct Router {
+    routes: Vec<Route>,
+}
+
+impl Router {
+    fn new() -> Self {
+        Router { routes: Vec::new() }
+    }
+
+    fn register(&mut self, method: HttpMethod, pattern: &str, handler: Handler) {
+        let segments = pattern
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(|segment| {
+                if let Some(name) = segment.strip_prefix(':') {
+                    RouteSegment::Param(name.to_string())
+                } else if let Some(name) = segment.strip_prefix('*') {
+                    RouteSegment::Wildcard(name.to_string())
+                } else {
+                    RouteSegment::Static(segment.to_string())
+                }
+            })
+            .collect();
+        self.routes.push(Route {
+            method,
+            segments,
+            handler,
+        });
+    }
+
+    fn match_route(&self, method: &HttpMethod, path: &str) -> Option<(Handler, HashMap<String, String>)> {
+        self.routes
+            .iter()
+            .filter(|route| &route.method == method)
+            .filter_map(|route| {
+                match_segments(&route.segments, path).map(|(params, score)| (route.handler, params, score))
+            })
+            .max_by_key(|(_, _, score)| *score)
+            .map(|(handler, params, _)| (handler, params))
+    }
+}
+
+/// Matches `path` against `segments`, returning the extracted params and a
+/// specificity score (used to break ties when more than one route matches)
+/// on success.
+fn match_segments(segments: &[RouteSegment], path: &str) -> Option<(HashMap<String, String>, usize)> {
+    let mut path_segments = path.split('/').filter(|segment| !segment.is_empty());
+    let mut params = HashMap::new();
+    let mut score = 0usize;
+
+    for segment in segments {
+        match segment {
+            RouteSegment::Wildcard(name) => {
+                let rest: Vec<&str> = path_segments.by_ref().collect();
+                params.insert(name.clone(), rest.join("/"));
+                return Some((params, score));
+            }
+            RouteSegment::Static(expected) => {
+                if path_segments.next()? != expected {
+                    return None;
+                }
+                score += 2;
+            }
+            RouteSegment::Param(name) => {
+                params.insert(name.clone(), path_segments.next()?.to_string());
+                score += 1;
+            }
         }
-        _ => respond_not_found(stream, None, None),
     }
+
+    if path_segments.next().is_some() {
+        return None; // leftover path segments with no wildcard to absorb them
+    }
+    Some((params, score))
+}
+
+fn build_router() -> Router {
+    let mut router = Router::new();
+    router.register(HttpMethod::GET, "/", endpoint_root);
+    router.register(HttpMethod::GET, "/echo/*msg", endpoint_get_echo);
+    router.register(HttpMethod::GET, "/user-agent", endpoint_get_user_agent);
+    router.register(HttpMethod::GET, "/files/*path", endpoint_get_files);
+    router.register(HttpMethod::POST, "/files/*path", endpoint_post_files);
+    router
 }
 
 fn parse_request_line(request_line: &str) -> Option<(HttpMethod, &str, HttpVersion)> {
@@ -266,76 +537,294 @@ fn parse_request_line(request_line: &str) -> Option<(HttpMethod, &str, HttpVersi
     None
 }
 
-fn endpoint_get_echo(stream: TcpStream, path: &str) {
-    let resp_value: String = path
-        .split("/")
-        .skip(2) // skip the inital path / and the echo/ portion as well
-        .collect::<Vec<&str>>()
-        .join("/"); // last join incase the string has more "/" chars in it
-    respond_string_body(stream, resp_value, None);
+const HTTP_DATE_WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const HTTP_DATE_MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Formats `time` as an RFC 7231 IMF-fixdate, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`,
+/// for use in the `Last-Modified` response header.
+fn format_http_date(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0) as i64;
+    let days = secs.div_euclid(86400);
+    let time_of_day = secs.rem_euclid(86400);
+
+    let (year, month, day) = civil_from_days(days);
+    let weekday = HTTP_DATE_WEEKDAYS[((days + 4).rem_euclid(7)) as usize];
+    let month_name = HTTP_DATE_MONTHS[(month - 1) as usize];
+    let (hour, minute, second) = (time_of_day / 3600, (time_of_day / 60) % 60, time_of_day % 60);
+
+    format!(
+        "{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT",
+        weekday, day, month_name, year, hour, minute, second
+    )
+}
+
+/// Parses an RFC 7231 IMF-fixdate, the form this server emits and the one
+/// `curl`/browsers send back in `If-Modified-Since`. Other historical date
+/// formats from the RFC are not accepted.
+fn parse_http_date(value: &str) -> Option<SystemTime> {
+    // "Sun, 06 Nov 1994 08:49:37 GMT"
+    let rest = value.split_once(", ")?.1;
+    let mut fields = rest.split_whitespace();
+    let day: i64 = fields.next()?.parse().ok()?;
+    let month_name = fields.next()?;
+    let month = 1 + HTTP_DATE_MONTHS.iter().position(|m| *m == month_name)? as i64;
+    let year: i64 = fields.next()?.parse().ok()?;
+    let mut time_fields = fields.next()?.splitn(3, ':');
+    let hour: i64 = time_fields.next()?.parse().ok()?;
+    let minute: i64 = time_fields.next()?.parse().ok()?;
+    let second: i64 = time_fields.next()?.parse().ok()?;
+
+    let days = days_from_civil(year, month, day);
+    let secs = days * 86400 + hour * 3600 + minute * 60 + second;
+    Some(UNIX_EPOCH + std::time::Duration::from_secs(secs.max(0) as u64))
+}
+
+/// Howard Hinnant's days-since-epoch <-> civil-date algorithm, used because
+/// the standard library has no calendar support and this server has no date
+/// crate dependency.
+fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = (z - era * 146097) as u64;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let y = yoe as i64 + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = if m <= 2 { y + 1 } else { y };
+    (y, m, d)
 }
 
-fn endpoint_get_user_agent(stream: TcpStream, header_parser: &HttpRequestHeaderParser) {
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = (y - era * 400) as u64;
+    let mp = if month > 2 { month - 3 } else { month + 9 } as u64;
+    let doy = (153 * mp + 2) / 5 + day as u64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe as i64 - 719468
+}
+
+fn endpoint_root(
+    stream: &mut TcpStream,
+    _params: &HashMap<String, String>,
+    _header_parser: &HttpRequestHeaderParser,
+    _body: Option<HttpRequestBody>,
+    keep_alive: bool,
+) -> bool {
+    respond_ok(stream, None, None, keep_alive)
+}
+
+fn endpoint_get_echo(
+    stream: &mut TcpStream,
+    params: &HashMap<String, String>,
+    header_parser: &HttpRequestHeaderParser,
+    _body: Option<HttpRequestBody>,
+    keep_alive: bool,
+) -> bool {
+    let resp_value = params.get("msg").cloned().unwrap_or_default();
+    respond_string_body(stream, resp_value, None, header_parser, keep_alive)
+}
+
+fn endpoint_get_user_agent(
+    stream: &mut TcpStream,
+    _params: &HashMap<String, String>,
+    header_parser: &HttpRequestHeaderParser,
+    _body: Option<HttpRequestBody>,
+    keep_alive: bool,
+) -> bool {
     if let Some(user_agent) = header_parser.get_header_value(HttpRequestHeaders::UserAgent) {
-        respond_string_body(stream, user_agent.to_string(), None);
+        respond_string_body(stream, user_agent.to_string(), None, header_parser, keep_alive)
     } else {
-        respond_not_found(stream, None, None);
+        respond_not_found(stream, None, None, keep_alive)
     }
 }
 
-fn endpoint_get_files(stream: TcpStream, path: &str) {
-    let file_name: String = path
-        .split("/")
-        .skip(2) // skip the inital path / and the files/ portion as well
-        .collect::<Vec<&str>>()
-        .join("/"); // last join incase this is a path with sub-directories
+/// Computes a weak ETag from a file's modification time and size, the way
+/// static-file handlers in frameworks like actix-web do for a quick
+/// without-hashing validator.
+fn weak_etag(modified: SystemTime, len: u64) -> String {
+    let mtime_secs = modified
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    format!("W/\"{}-{}\"", mtime_secs, len)
+}
+
+fn endpoint_get_files(
+    stream: &mut TcpStream,
+    params: &HashMap<String, String>,
+    header_parser: &HttpRequestHeaderParser,
+    _body: Option<HttpRequestBody>,
+    keep_alive: bool,
+) -> bool {
+    let file_name = params.get("path").cloned().unwrap_or_default();
     let env_args: Vec<String> = env::args().collect();
     let mut file_path = env_args[2].clone();
     file_path.push_str(&file_name);
 
-    if let Ok(mut file) = fs::File::open(Path::new(&file_path)) {
-        let mut buf = Vec::new();
-        file.read_to_end(&mut buf).unwrap();
-        respond_byte_body(stream, buf, Some("application/octet-stream".to_string()));
+    let metadata = match fs::metadata(&file_path) {
+        Ok(metadata) => metadata,
+        Err(_) => return respond_not_found(stream, None, None, keep_alive),
+    };
+    if metadata.is_dir() {
+        return respond_not_found(stream, None, None, keep_alive);
+    }
+    let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+    let etag = weak_etag(modified, metadata.len());
+    let last_modified = format_http_date(modified);
+
+    // If-None-Match takes precedence over If-Modified-Since when both are present.
+    let not_modified = if let Some(if_none_match) = header_parser.get_header_value(HttpRequestHeaders::IfNoneMatch)
+    {
+        if_none_match.trim() == etag
+    } else if let Some(if_modified_since) =
+        header_parser.get_header_value(HttpRequestHeaders::IfModifiedSince)
+    {
+        parse_http_date(if_modified_since).is_some_and(|since| modified <= since)
+    } else {
+        false
+    };
+
+    if not_modified {
+        return respond_not_modified(stream, &etag, &last_modified, keep_alive);
+    }
+
+    let mut file = match fs::File::open(Path::new(&file_path)) {
+        Ok(file) => file,
+        Err(_) => return respond_not_found(stream, None, None, keep_alive),
+    };
+    let file_len = metadata.len();
+
+    if let Some(range) = header_parser.get_header_value(HttpRequestHeaders::Range) {
+        return match parse_byte_range(range, file_len) {
+            Some(Ok((start, end))) => {
+                let mut buf = vec![0u8; (end - start + 1) as usize];
+                if file.seek(SeekFrom::Start(start)).is_err() || file.read_exact(&mut buf).is_err() {
+                    return respond_internal_server_error(
+                        stream,
+                        None,
+                        Some("failed to read file.".to_string()),
+                        keep_alive,
+                    );
+                }
+                respond_partial_content(
+                    stream,
+                    buf,
+                    Some("application/octet-stream".to_string()),
+                    start,
+                    end,
+                    file_len,
+                    keep_alive,
+                )
+            }
+            Some(Err(())) => respond_range_not_satisfiable(stream, file_len, keep_alive),
+            None => {
+                let mut buf = Vec::new();
+                file.read_to_end(&mut buf).unwrap();
+                respond_byte_body_with_metadata(
+                    stream,
+                    buf,
+                    Some("application/octet-stream".to_string()),
+                    &etag,
+                    &last_modified,
+                    header_parser,
+                    keep_alive,
+                )
+            }
+        };
+    }
+
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf).unwrap();
+    respond_byte_body_with_metadata(
+        stream,
+        buf,
+        Some("application/octet-stream".to_string()),
+        &etag,
+        &last_modified,
+        header_parser,
+        keep_alive,
+    )
+}
+
+/// Parses a `Range: bytes=...` request header into an inclusive `(start, end)`
+/// byte range, supporting the `start-end`, `start-`, and `-suffix` forms.
+/// Returns `None` when there's no (usable) range to honor, in which case the
+/// caller should fall back to a normal full-body response; returns
+/// `Some(Err(()))` when the range can't be satisfied against `file_len`.
+fn parse_byte_range(value: &str, file_len: u64) -> Option<Result<(u64, u64), ()>> {
+    let spec = value.strip_prefix("bytes=")?;
+    let (start_str, end_str) = spec.split_once('-')?;
+
+    if start_str.is_empty() {
+        let suffix_len: u64 = end_str.parse().ok()?;
+        if suffix_len == 0 || file_len == 0 {
+            return Some(Err(()));
+        }
+        let len = suffix_len.min(file_len);
+        return Some(Ok((file_len - len, file_len - 1)));
+    }
+
+    let start: u64 = start_str.parse().ok()?;
+    let end = if end_str.is_empty() {
+        file_len.saturating_sub(1)
     } else {
-        respond_not_found(stream, None, None);
+        end_str.parse().ok()?
+    };
+
+    if file_len == 0 || start > end || start >= file_len {
+        return Some(Err(()));
     }
+    Some(Ok((start, end.min(file_len - 1))))
 }
 
 fn endpoint_post_files(
-    stream: TcpStream,
-    path: &str,
+    stream: &mut TcpStream,
+    params: &HashMap<String, String>,
     headers: &HttpRequestHeaderParser,
     body: Option<HttpRequestBody>,
-) {
-    let file_name: String = path
-        .split("/")
-        .skip(2) // skip the inital path / and the files/ portion as well
-        .collect::<Vec<&str>>()
-        .join("/"); // last join incase this is a path with sub-directories
+    keep_alive: bool,
+) -> bool {
+    let file_name = params.get("path").cloned().unwrap_or_default();
     let env_args: Vec<String> = env::args().collect();
     let mut file_path = env_args[2].clone();
     file_path.push_str(&file_name);
 
     if !headers.is_content_type(ContentType::ApplicationOctetStream) {
-        respond_bad_request(stream, None, Some("unexpected content type".to_string()));
-        return;
+        return respond_bad_request(
+            stream,
+            None,
+            Some("unexpected content type".to_string()),
+            keep_alive,
+        );
     }
 
     if let Ok(_) = fs::metadata(&file_path) {
-        respond_conflict(stream, None, Some("file already exists.".to_string()));
-        return;
+        return respond_conflict(
+            stream,
+            None,
+            Some("file already exists.".to_string()),
+            keep_alive,
+        );
     }
 
     match body {
         Some(body) => {
             if let Ok(mut file) = fs::File::create_new(Path::new(&file_path)) {
                 match file.write_all(body.as_bytes()) {
-                    Ok(_) => respond_created(stream, None, None),
+                    Ok(_) => respond_created(stream, None, None, keep_alive),
                     Err(_) => respond_internal_server_error(
                         stream,
                         None,
                         Some("failed to write to file.".to_string()),
+                        keep_alive,
                     ),
                 }
             } else {
@@ -343,104 +832,283 @@ fn endpoint_post_files(
                     stream,
                     None,
                     Some("failed to create file.".to_string()),
-                );
+                    keep_alive,
+                )
             }
         }
-        None => respond_bad_request(stream, None, Some("No body provided".to_string())),
+        None => respond_bad_request(stream, None, Some("No body provided".to_string()), keep_alive),
     }
 }
 
-fn respond_string_body(stream: TcpStream, body: String, content_type: Option<String>) {
-    let header = HttpResponseHeaderBuilder::new()
+/// Gzips `body` when the client's `Accept-Encoding` header advertises `gzip`,
+/// returning the (possibly compressed) bytes alongside the `Content-Encoding`
+/// value to send, if any. When gzip isn't offered the body passes through
+/// unchanged so existing clients keep working.
+fn maybe_compress_body(
+    body: Vec<u8>,
+    header_parser: &HttpRequestHeaderParser,
+) -> (Vec<u8>, Option<String>) {
+    if !header_parser.accepts_encoding("gzip") {
+        return (body, None);
+    }
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    if encoder.write_all(&body).is_err() {
+        return (body, None);
+    }
+    match encoder.finish() {
+        Ok(compressed) => (compressed, Some("gzip".to_string())),
+        Err(_) => (body, None),
+    }
+}
+
+fn respond_string_body(
+    stream: &mut TcpStream,
+    body: String,
+    content_type: Option<String>,
+    header_parser: &HttpRequestHeaderParser,
+    keep_alive: bool,
+) -> bool {
+    let (body, content_encoding) = maybe_compress_body(body.into_bytes(), header_parser);
+
+    let mut builder = HttpResponseHeaderBuilder::new().add(
+        HttpResponseHeaders::ContentType,
+        content_type.unwrap_or("text/plain".to_string()),
+    );
+    if let Some(content_encoding) = content_encoding {
+        builder = builder.add(HttpResponseHeaders::ContentEncoding, content_encoding);
+    }
+    let header = builder.get_response_string();
+
+    write_response(stream, 200, "OK", Some(header), Some(body), keep_alive)
+}
+
+/// Like `respond_string_body`, but takes a raw byte body and also advertises
+/// the validators a client needs to make conditional requests against this
+/// same resource.
+fn respond_byte_body_with_metadata(
+    stream: &mut TcpStream,
+    body: Vec<u8>,
+    content_type: Option<String>,
+    etag: &str,
+    last_modified: &str,
+    header_parser: &HttpRequestHeaderParser,
+    keep_alive: bool,
+) -> bool {
+    let (body, content_encoding) = maybe_compress_body(body, header_parser);
+
+    let mut builder = HttpResponseHeaderBuilder::new()
         .add(
             HttpResponseHeaders::ContentType,
             content_type.unwrap_or("text/plain".to_string()),
         )
-        .add(HttpResponseHeaders::ContentLength, body.len().to_string())
-        .get_response_string();
+        .add(HttpResponseHeaders::ETag, etag.to_string())
+        .add(HttpResponseHeaders::LastModified, last_modified.to_string());
+    // The Range path always seeks/reads the raw file, so Accept-Ranges only
+    // holds for the uncompressed representation; don't advertise it once
+    // this response has been gzipped, or a client following up with a Range
+    // request would get raw bytes sliced out of a representation it never saw.
+    if let Some(content_encoding) = content_encoding {
+        builder = builder.add(HttpResponseHeaders::ContentEncoding, content_encoding);
+    } else {
+        builder = builder.add(HttpResponseHeaders::AcceptRanges, "bytes".to_string());
+    }
+    let header = builder.get_response_string();
+    write_response(stream, 200, "OK", Some(header), Some(body), keep_alive)
+}
 
-    respond_ok(stream, Some(header), Some(body));
+/// Writes a bare `100 Continue` status line so a client that sent
+/// `Expect: 100-continue` will go ahead and send its body. This is an
+/// interim response, so it has no headers or body of its own and must not
+/// be followed by the final response until the body has actually arrived.
+fn respond_continue(stream: &mut TcpStream) {
+    stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").unwrap();
 }
 
-fn respond_byte_body(mut stream: TcpStream, body: Vec<u8>, content_type: Option<String>) {
+fn respond_not_modified(
+    stream: &mut TcpStream,
+    etag: &str,
+    last_modified: &str,
+    keep_alive: bool,
+) -> bool {
+    let header = HttpResponseHeaderBuilder::new()
+        .add(HttpResponseHeaders::ETag, etag.to_string())
+        .add(HttpResponseHeaders::LastModified, last_modified.to_string())
+        .get_response_string();
+    write_response(stream, 304, "Not Modified", Some(header), None, keep_alive)
+}
+
+/// Responds `206 Partial Content` with the inclusive byte range `start..=end`
+/// out of a file of total size `file_len`.
+fn respond_partial_content(
+    stream: &mut TcpStream,
+    body: Vec<u8>,
+    content_type: Option<String>,
+    start: u64,
+    end: u64,
+    file_len: u64,
+    keep_alive: bool,
+) -> bool {
     let header = HttpResponseHeaderBuilder::new()
         .add(
             HttpResponseHeaders::ContentType,
             content_type.unwrap_or("text/plain".to_string()),
         )
-        .add(HttpResponseHeaders::ContentLength, body.len().to_string())
+        .add(HttpResponseHeaders::AcceptRanges, "bytes".to_string())
+        .add(
+            HttpResponseHeaders::ContentRange,
+            format!("bytes {}-{}/{}", start, end, file_len),
+        )
+        .get_response_string();
+    write_response(
+        stream,
+        206,
+        "Partial Content",
+        Some(header),
+        Some(body),
+        keep_alive,
+    )
+}
+
+fn respond_range_not_satisfiable(stream: &mut TcpStream, file_len: u64, keep_alive: bool) -> bool {
+    let header = HttpResponseHeaderBuilder::new()
+        .add(
+            HttpResponseHeaders::ContentRange,
+            format!("bytes */{}", file_len),
+        )
         .get_response_string();
-    let header_buf = format!("HTTP/1.1 {} {}\r\n{}\r\n", 200, "OK", header,);
+    write_response(
+        stream,
+        416,
+        "Range Not Satisfiable",
+        Some(header),
+        None,
+        keep_alive,
+    )
+}
+
+/// Writes a full status line, headers (appending `Connection: close` when
+/// `keep_alive` is false), and body to `stream`. Returns `keep_alive`
+/// unchanged so callers can chain straight into the connection loop.
+fn write_response(
+    stream: &mut TcpStream,
+    status_code: u16,
+    status_text: &str,
+    headers: Option<String>,
+    body: Option<Vec<u8>>,
+    keep_alive: bool,
+) -> bool {
+    // With keep-alive the connection no longer closes to mark the end of the
+    // body, so every response must announce its length explicitly.
+    let mut headers = headers.unwrap_or_default();
+    headers += HttpResponseHeaders::ContentLength.as_str();
+    headers += &body.as_ref().map_or(0, |b| b.len()).to_string();
+    headers += CLRF;
+    if !keep_alive {
+        headers += HttpResponseHeaders::Connection.as_str();
+        headers += "close";
+        headers += CLRF;
+    }
+    let header_buf = format!("HTTP/1.1 {} {}\r\n{}\r\n", status_code, status_text, headers);
     stream.write_all(header_buf.as_bytes()).unwrap();
-    stream.write_all(&body).unwrap();
+    if let Some(body) = body {
+        stream.write_all(&body).unwrap();
+    }
+    keep_alive
 }
 
-fn respond_ok(mut stream: TcpStream, headers: Option<String>, body: Option<String>) {
-    let buf = format!(
-        "HTTP/1.1 {} {}\r\n{}\r\n{}",
+fn respond_ok(
+    stream: &mut TcpStream,
+    headers: Option<String>,
+    body: Option<String>,
+    keep_alive: bool,
+) -> bool {
+    write_response(
+        stream,
         200,
         "OK",
-        headers.unwrap_or_default(),
-        body.unwrap_or_default()
-    );
-    stream.write(&buf.as_bytes()).unwrap();
+        headers,
+        body.map(|b| b.into_bytes()),
+        keep_alive,
+    )
 }
 
-fn respond_not_found(mut stream: TcpStream, headers: Option<String>, body: Option<String>) {
-    let buf = format!(
-        "HTTP/1.1 {} {}\r\n{}\r\n{}",
+fn respond_not_found(
+    stream: &mut TcpStream,
+    headers: Option<String>,
+    body: Option<String>,
+    keep_alive: bool,
+) -> bool {
+    write_response(
+        stream,
         404,
         "Not Found",
-        headers.unwrap_or_default(),
-        body.unwrap_or_default()
-    );
-    stream.write(&buf.as_bytes()).unwrap();
+        headers,
+        body.map(|b| b.into_bytes()),
+        keep_alive,
+    )
 }
 
-fn respond_created(mut stream: TcpStream, headers: Option<String>, body: Option<String>) {
-    let buf = format!(
-        "HTTP/1.1 {} {}\r\n{}\r\n{}",
+fn respond_created(
+    stream: &mut TcpStream,
+    headers: Option<String>,
+    body: Option<String>,
+    keep_alive: bool,
+) -> bool {
+    write_response(
+        stream,
         201,
         "Created",
-        headers.unwrap_or_default(),
-        body.unwrap_or_default()
-    );
-    stream.write(&buf.as_bytes()).unwrap();
+        headers,
+        body.map(|b| b.into_bytes()),
+        keep_alive,
+    )
 }
 
-fn respond_conflict(mut stream: TcpStream, headers: Option<String>, body: Option<String>) {
-    let buf = format!(
-        "HTTP/1.1 {} {}\r\n{}\r\n{}",
+fn respond_conflict(
+    stream: &mut TcpStream,
+    headers: Option<String>,
+    body: Option<String>,
+    keep_alive: bool,
+) -> bool {
+    write_response(
+        stream,
         409,
         "Conflict",
-        headers.unwrap_or_default(),
-        body.unwrap_or_default()
-    );
-    stream.write(&buf.as_bytes()).unwrap();
+        headers,
+        body.map(|b| b.into_bytes()),
+        keep_alive,
+    )
 }
 
 fn respond_internal_server_error(
-    mut stream: TcpStream,
+    stream: &mut TcpStream,
     headers: Option<String>,
     body: Option<String>,
-) {
-    let buf = format!(
-        "HTTP/1.1 {} {}\r\n{}\r\n{}",
+    keep_alive: bool,
+) -> bool {
+    write_response(
+        stream,
         500,
         "Internal Server Error",
-        headers.unwrap_or_default(),
-        body.unwrap_or_default()
-    );
-    stream.write(&buf.as_bytes()).unwrap();
+        headers,
+        body.map(|b| b.into_bytes()),
+        keep_alive,
+    )
 }
 
-fn respond_bad_request(mut stream: TcpStream, headers: Option<String>, body: Option<String>) {
-    let buf = format!(
-        "HTTP/1.1 {} {}\r\n{}\r\n{}",
+fn respond_bad_request(
+    stream: &mut TcpStream,
+    headers: Option<String>,
+    body: Option<String>,
+    keep_alive: bool,
+) -> bool {
+    write_response(
+        stream,
         400,
         "Bad Request",
-        headers.unwrap_or_default(),
-        body.unwrap_or_default()
-    );
-    stream.write(&buf.as_bytes()).unwrap();
+        headers,
+        body.map(|b| b.into_bytes()),
+        keep_alive,
+    )
 }